@@ -0,0 +1,134 @@
+use log::{error, info};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use toml::Table;
+
+/// Read the target triple to build for, preferring an explicit override
+/// (the `--target` CLI arg) over the project's `.cargo/config.toml`.
+pub fn detect_target(project_path: &Path, override_target: Option<&str>) -> Result<String, String> {
+    if let Some(target) = override_target {
+        return Ok(target.to_string());
+    }
+    let config_path = project_path.join(".cargo/config.toml");
+    let config_bytes = std::fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read {}: {e}", config_path.display()))?;
+    let config: Table = toml::from_str(&config_bytes)
+        .map_err(|e| format!("Failed to parse {}: {e}", config_path.display()))?;
+    config
+        .get("build")
+        .and_then(|build| build.get("target"))
+        .and_then(|target| target.as_str())
+        .map(|target| target.to_string())
+        .ok_or_else(|| {
+            format!(
+                "No build.target found in {}, pass --target explicitly",
+                config_path.display()
+            )
+        })
+}
+
+/// Pick the `*-objcopy` binary that matches a given target triple.
+pub fn objcopy_for_target(triple: &str) -> String {
+    if triple.starts_with("thumbv") || triple.ends_with("-none-eabi") || triple.ends_with("-none-eabihf") {
+        "arm-none-eabi-objcopy".to_string()
+    } else if triple.starts_with("riscv32") || triple.starts_with("riscv64") {
+        "riscv64-unknown-elf-objcopy".to_string()
+    } else {
+        format!("{triple}-objcopy")
+    }
+}
+
+/// Pick the bfd input format to pass to objcopy's `-I` flag for a given
+/// target triple, or `None` to omit `-I` and let objcopy auto-detect the
+/// ELF's own format.
+pub fn objcopy_input_format(triple: &str) -> Option<&'static str> {
+    if triple.starts_with("thumbv") || triple.ends_with("-none-eabi") || triple.ends_with("-none-eabihf") {
+        Some("elf32-littlearm")
+    } else if triple.starts_with("riscv32") {
+        Some("elf32-littleriscv")
+    } else if triple.starts_with("riscv64") {
+        Some("elf64-littleriscv")
+    } else {
+        None
+    }
+}
+
+/// Make sure `objcopy` is reachable on `PATH`, exiting with an actionable
+/// hint if it is not.
+pub fn check_objcopy_installed(objcopy: &str) {
+    let found = std::process::Command::new(objcopy)
+        .arg("--version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok();
+    if !found {
+        error!(
+            "{objcopy} not found on PATH. Install the matching binutils package (e.g. `apt install binutils-arm-none-eabi`) or add your toolchain's objcopy to PATH."
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Stream a child process's stdout/stderr live and return its exit status.
+pub fn run_command_live(mut cmd: Child, what: &str) -> ExitStatus {
+    let stdout = cmd.stdout.take().expect("Failed to capture stdout");
+    let stderr = cmd.stderr.take().expect("Failed to capture stderr");
+
+    let handle_stdout = std::thread::spawn(move || {
+        let reader = std::io::BufReader::new(stdout);
+        for line in reader.lines() {
+            println!("{}", line.expect("Failed to read line from stdout"));
+        }
+    });
+
+    let handle_stderr = std::thread::spawn(move || {
+        let reader = std::io::BufReader::new(stderr);
+        for line in reader.lines() {
+            eprintln!("{}", line.expect("Failed to read line from stderr"));
+        }
+    });
+
+    handle_stdout.join().expect("Failed to join stdout thread");
+    handle_stderr.join().expect("Failed to join stderr thread");
+
+    cmd.wait()
+        .unwrap_or_else(|e| panic!("Failed to wait on {what}: {e}"))
+}
+
+/// Check a child's exit status the way a well-behaved xtask does: a
+/// non-zero code is reported and propagated, a missing code means the
+/// process was killed by a signal.
+pub fn check_exit_status(status: ExitStatus, what: &str) {
+    match status.code() {
+        Some(0) => info!("{what} succeeded"),
+        Some(code) => {
+            error!("{what} failed with exit code {code}");
+            std::process::exit(code);
+        }
+        None => {
+            error!("{what} was terminated by a signal");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run `cargo build --release --target <target>` for the project, streaming
+/// output live, and return the directory the resulting ELF will be in.
+pub fn cargo_build_release(project_path: &Path, target: &str) -> PathBuf {
+    info!("Running cargo build --release --target {target}");
+    let cmd = std::process::Command::new("cargo")
+        .arg("build")
+        .arg("--release")
+        .arg("--target")
+        .arg(target)
+        .current_dir(project_path)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to run cargo build");
+    let status = run_command_live(cmd, "cargo build --release");
+    check_exit_status(status, "cargo build --release");
+    project_path.join("target").join(target).join("release")
+}
@@ -0,0 +1,75 @@
+use crate::git;
+use log::{error, info};
+use std::path::PathBuf;
+use toml::Table;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Level {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// Bump the project's version, resetting lower components, write it back
+/// into `Cargo.toml`, and tag the result.
+pub async fn bump(path: String, level: Level, pre: Option<String>) {
+    let path = PathBuf::from(path);
+    let cargo_toml_path = path.join("Cargo.toml");
+    let contents = match tokio::fs::read_to_string(&cargo_toml_path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            error!("Failed to read {}: {e}", cargo_toml_path.display());
+            std::process::exit(1);
+        }
+    };
+    let table: Table = match toml::from_str(&contents) {
+        Ok(table) => table,
+        Err(e) => {
+            error!("Failed to parse {}: {e}", cargo_toml_path.display());
+            std::process::exit(1);
+        }
+    };
+
+    let mut version = match git::describe_version(&path, &table) {
+        Ok(version) => version,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    match level {
+        Level::Major => {
+            version.major += 1;
+            version.minor = 0;
+            version.patch = 0;
+        }
+        Level::Minor => {
+            version.minor += 1;
+            version.patch = 0;
+        }
+        Level::Patch => {
+            version.patch += 1;
+        }
+    }
+    version.pre = match pre {
+        Some(ident) => match semver::Prerelease::new(&ident) {
+            Ok(pre) => pre,
+            Err(e) => {
+                error!("Invalid --pre identifier `{ident}`: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => semver::Prerelease::EMPTY,
+    };
+
+    if let Err(e) = git::write_cargo_version(&path, &version) {
+        error!("{e}");
+        std::process::exit(1);
+    }
+    info!("Bumped version to {version}");
+    if let Err(e) = git::create_tag(&path, &version) {
+        error!("{e}");
+        std::process::exit(1);
+    }
+    info!("Created git tag v{version}");
+}
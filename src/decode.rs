@@ -0,0 +1,125 @@
+use crate::ota::{self, Compression, OtaHead};
+use log::{error, info};
+use std::io::Read;
+use std::path::PathBuf;
+use zerocopy::TryFromBytes;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+pub async fn decode(path: String, extract: Option<String>, format: Option<Format>) {
+    let path = PathBuf::from(path);
+    let file_bytes = tokio::fs::read(path).await.expect("Failed to read file");
+    if file_bytes.len() < 512 {
+        error!("File is too short to be an ota bin file");
+        std::process::exit(1);
+    }
+    let ota_head_bytes = &file_bytes[0..512];
+    let ota_head = match OtaHead::try_read_from_bytes(ota_head_bytes) {
+        Ok(ota_head) => ota_head,
+        Err(e) => {
+            error!("Invalid ota head {}", e);
+            std::process::exit(1);
+        }
+    };
+    if ota_head.magic_word != ota::MAGIC_WORD {
+        error!("Invalid magic word");
+        std::process::exit(1);
+    }
+    let crc = ota::crc16(&file_bytes[6..]);
+    let expected_crc = ota_head.crc;
+    if crc != expected_crc {
+        error!(
+            "CRC mismatch, expected 0x{:X}, got 0x{:X}",
+            expected_crc, crc
+        );
+        std::process::exit(1);
+    }
+    let build_time =
+        match chrono::DateTime::<chrono::Utc>::from_timestamp(ota_head.timestamp as i64, 0) {
+            Some(t) => t.to_rfc3339(),
+            None => "Unknown".to_string(),
+        };
+    let firmware_size = ota_head.size;
+    let compression = ota_head.compression();
+    let uncompressed_size = ota_head.uncompressed_size();
+    let project_name = String::from_utf8_lossy(&ota_head.project_name)
+        .trim_end_matches('\0')
+        .to_string();
+    let version = String::from_utf8_lossy(&ota_head.version)
+        .trim_end_matches('\0')
+        .to_string();
+
+    let magic_word = ota_head.magic_word;
+
+    match format {
+        Some(Format::Json) => {
+            let out = serde_json::json!({
+                "magic": format!("0x{:X}", magic_word),
+                "crc": format!("0x{:04X}", expected_crc),
+                "version": version,
+                "semver": ota_head.semver().to_string(),
+                "project_name": project_name,
+                "build_time": build_time,
+                "firmware_size": firmware_size,
+                "uncompressed_size": uncompressed_size,
+                "compression": ota::compression_name(compression),
+            });
+            println!("{}", serde_json::to_string(&out).unwrap());
+        }
+        None | Some(Format::Text) => {
+            info!(
+                "Valid Bin File!:\n  Project Name: {}\n  Version: {}\n  Semantic Version: {}\n  Created at: {}\n  Compression: {}\n  Firmware Size: {}B ({:.2}KB)\n  Uncompressed Size: {}B ({:.2}KB)",
+                project_name,
+                version,
+                ota_head.semver(),
+                build_time,
+                ota::compression_name(compression),
+                firmware_size,
+                firmware_size as f32 / 1024.0,
+                uncompressed_size,
+                uncompressed_size as f32 / 1024.0
+            );
+        }
+    }
+
+    if let Some(extract) = extract {
+        let payload = &file_bytes[512..];
+        if compression.is_none() && uncompressed_size as usize > payload.len() {
+            error!(
+                "Uncompressed size in header ({}B) exceeds payload length ({}B), header is corrupted",
+                uncompressed_size,
+                payload.len()
+            );
+            std::process::exit(1);
+        }
+        let inflated = match compression {
+            Some(Compression::Gzip) => {
+                let mut decoder = flate2::read::GzDecoder::new(payload);
+                let mut inflated = Vec::new();
+                decoder
+                    .read_to_end(&mut inflated)
+                    .expect("Failed to inflate gzip payload");
+                inflated
+            }
+            // Uncompressed payloads are padded to the next 8-byte boundary
+            // with 0xFF by `encode`; drop that trailing alignment padding.
+            None => payload[..uncompressed_size as usize].to_vec(),
+        };
+        if inflated.len() != uncompressed_size as usize {
+            error!(
+                "Inflated size mismatch, expected {}B, got {}B",
+                uncompressed_size,
+                inflated.len()
+            );
+            std::process::exit(1);
+        }
+        tokio::fs::write(&extract, &inflated)
+            .await
+            .expect("Failed to write extracted binary");
+        info!("Extracted firmware binary to {}", extract);
+    }
+}
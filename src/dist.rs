@@ -0,0 +1,77 @@
+use crate::encode;
+use crate::ota::{self, Compression};
+use flate2::write::GzEncoder;
+use log::{error, info};
+use std::fs::File;
+use std::path::PathBuf;
+
+/// Encode the project's firmware and bundle it, together with a
+/// `manifest.json` and any `--include`d extra files, into a single
+/// `{project}-{version}.tar.gz` release archive.
+pub async fn dist(
+    path: String,
+    build: bool,
+    target: Option<String>,
+    compress: Option<Compression>,
+    include: Vec<String>,
+    allow_dirty: bool,
+) {
+    let project_path = PathBuf::from(&path);
+    let Some((ota_path, ota_head)) =
+        encode::encode(path, build, target, compress, allow_dirty).await
+    else {
+        error!("Failed to produce ota bin file, aborting dist");
+        std::process::exit(1);
+    };
+
+    let project_name = String::from_utf8_lossy(&ota_head.project_name)
+        .trim_end_matches('\0')
+        .to_string();
+    let git_version = String::from_utf8_lossy(&ota_head.version)
+        .trim_end_matches('\0')
+        .to_string();
+    let semver = ota_head.semver();
+    let timestamp = ota_head.timestamp;
+    let firmware_size = ota_head.size;
+    let crc = ota_head.crc;
+
+    let manifest = serde_json::json!({
+        "project": project_name,
+        "version": git_version,
+        "semver": semver.to_string(),
+        "timestamp": timestamp,
+        "firmware_size": firmware_size,
+        "crc": format!("0x{:04X}", crc),
+        "compression": ota::compression_name(ota_head.compression()),
+    });
+    let manifest_file = tempfile::NamedTempFile::new().expect("Failed to create temp manifest");
+    std::fs::write(
+        manifest_file.path(),
+        serde_json::to_vec_pretty(&manifest).expect("Failed to serialize manifest"),
+    )
+    .expect("Failed to write manifest.json");
+
+    let archive_name = format!("{}-{}.tar.gz", project_name, semver);
+    let archive_path = project_path.join(&archive_name);
+    let tar_gz = File::create(&archive_path).expect("Failed to create dist archive");
+    let encoder = GzEncoder::new(tar_gz, flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    tar.append_path_with_name(&ota_path, ota_path.file_name().unwrap())
+        .expect("Failed to add ota bin to archive");
+    tar.append_path_with_name(manifest_file.path(), "manifest.json")
+        .expect("Failed to add manifest.json to archive");
+    for extra in &include {
+        let extra_path = PathBuf::from(extra);
+        let name = extra_path
+            .file_name()
+            .unwrap_or_else(|| panic!("--include path has no file name: {extra}"));
+        tar.append_path_with_name(&extra_path, name)
+            .unwrap_or_else(|e| panic!("Failed to add {extra} to archive: {e}"));
+    }
+    tar.into_inner()
+        .expect("Failed to finish archive")
+        .finish()
+        .expect("Failed to finish gzip stream");
+
+    info!("Created dist archive at {}", archive_path.display());
+}
@@ -0,0 +1,185 @@
+use crate::build;
+use crate::git;
+use crate::ota::{self, Compression, OtaHead};
+use log::{error, info};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use toml::Table;
+use zerocopy::IntoBytes;
+
+/// Build (optionally) and encode a project's firmware into an OTA bin file.
+/// Returns the path to the created file and its final header on success.
+pub async fn encode(
+    path: String,
+    build: bool,
+    target: Option<String>,
+    compress: Option<Compression>,
+    allow_dirty: bool,
+) -> Option<(PathBuf, OtaHead)> {
+    let path = PathBuf::from(path);
+    // Check if the file exists
+    if !path.exists() {
+        error!("File does not exist: {}", path.display());
+        return None;
+    }
+    // Read the file
+    let file_bytes = tokio::fs::read(path.join("Cargo.toml")).await.unwrap();
+    let file: String = String::from_utf8(file_bytes).unwrap();
+    // Try to find "embassy" in the file
+    let embassy_index = file.find("embassy");
+    if embassy_index.is_none() {
+        error!(
+            "Cargo.toml does not seem contain embassy, are you sure this is a embedded project?"
+        );
+        std::process::exit(1);
+    }
+
+    if git::is_dirty(&path) && !allow_dirty {
+        error!("Working tree is dirty, refusing to build a release image. Pass --allow-dirty to override.");
+        std::process::exit(1);
+    }
+
+    let git_hash = git::git_hash(&path);
+    info!("Git hash: {}", git_hash);
+    let mut gh = git_hash.bytes().collect::<Vec<u8>>();
+    if gh.len() > 31 {
+        panic!("git hash is too long");
+    }
+    gh.push(0);
+    let mut version = [0u8; 32];
+    version[..gh.len()].copy_from_slice(&gh);
+
+    let value = toml::from_str::<Table>(&file).unwrap();
+    let project_name = value["package"]["name"].as_str().unwrap().to_string();
+    info!("Project name: {}", project_name);
+
+    let semver = match git::describe_version(&path, &value) {
+        Ok(semver) => semver,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    info!("Semantic version: {}", semver);
+
+    let target = match build::detect_target(&path, target.as_deref()) {
+        Ok(target) => target,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    info!("Target: {}", target);
+
+    let release_dir = if build {
+        build::cargo_build_release(&path, &target)
+    } else {
+        path.join("target").join(&target).join("release")
+    };
+    let bin_path = release_dir.join(project_name.clone());
+    if !bin_path.exists() {
+        error!(
+            "Bin file does not exist: {}. Did you run cargo build --release first, or pass --build?",
+            bin_path.display()
+        );
+        return None;
+    }
+
+    let objcopy = build::objcopy_for_target(&target);
+    build::check_objcopy_installed(&objcopy);
+
+    let mut objcopy_cmd = std::process::Command::new(&objcopy);
+    if let Some(input_format) = build::objcopy_input_format(&target) {
+        objcopy_cmd.arg("-I").arg(input_format);
+    }
+    let cmd = objcopy_cmd
+        .arg("-O")
+        .arg("binary")
+        .arg(bin_path.as_path())
+        .arg(path.join("xstd-app-tool-temp.bin").as_path())
+        .current_dir(path.clone())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("Failed to run {objcopy}: {e}"));
+    let status = build::run_command_live(cmd, &objcopy);
+    build::check_exit_status(status, &objcopy);
+    let project_name = project_name.as_bytes();
+    if project_name.len() > 15 {
+        panic!("project name is too long");
+    }
+    let mut project_name_string = project_name.to_vec();
+    project_name_string.push(0);
+    let mut project_name = [0u8; 16];
+    project_name[..project_name_string.len()].copy_from_slice(&project_name_string);
+
+    let raw_bytes = tokio::fs::read(path.join("xstd-app-tool-temp.bin"))
+        .await
+        .unwrap();
+    let uncompressed_size = raw_bytes.len() as u32;
+    let mut file_bytes = match compress {
+        Some(Compression::Gzip) => {
+            info!("Compressing payload with gzip");
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&raw_bytes).unwrap();
+            encoder.finish().unwrap()
+        }
+        None => raw_bytes,
+    };
+    // Fill the file to the nearest 8 bytes.
+    // This is important!
+    while file_bytes.len() % 8 != 0 {
+        file_bytes.push(0xFF);
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut ota_head = OtaHead {
+        magic_word: ota::MAGIC_WORD,
+        crc: 0,
+        version,
+        project_name,
+        timestamp,
+        size: file_bytes.len() as u32,
+        reserved: [0; 446],
+    };
+    ota_head.set_compression(compress, uncompressed_size);
+    ota_head.set_semver(&semver);
+    let mut ota_bytes = ota_head.as_bytes().to_vec();
+    while ota_bytes.len() < 512 {
+        ota_bytes.push(0xFF);
+    }
+    assert!(ota_bytes.len() == 512);
+
+    let file_name = format!(
+        "{}-{}-ota.bin",
+        value["package"]["name"].as_str().unwrap().to_string(),
+        git_hash
+    );
+    let file_path = path.join(file_name);
+    let mut file = File::create(file_path.clone()).unwrap();
+    info!("Created ota bin file at {}", file_path.display());
+    let final_length = 512 + file_bytes.len();
+    let mut total_bytes = [ota_bytes, file_bytes].concat();
+    assert!(total_bytes.len() == final_length);
+    let crc = ota::crc16(&total_bytes.as_slice()[6..]);
+    let crc_bytes = crc.to_le_bytes();
+    ota_head.crc = crc;
+    // little endian
+    total_bytes[4] = crc_bytes[0];
+    total_bytes[5] = crc_bytes[1];
+
+    info!("OTA head: {:?}", ota_head);
+    // Write the file
+    file.write_all(total_bytes.as_bytes()).unwrap();
+    file.sync_all().unwrap();
+    // Remove the temp file
+    std::fs::remove_file(path.join("xstd-app-tool-temp.bin")).unwrap();
+
+    Some((file_path, ota_head))
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+use toml::Table;
+
+/// Short git hash of `HEAD`, with a `-dirty` suffix when the tree has
+/// uncommitted changes.
+pub fn git_hash(path: &Path) -> String {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(path)
+        .output()
+        .expect("Failed to execute git command")
+        .stdout;
+    let hash = std::str::from_utf8(&output)
+        .expect("Failed to parse git hash")
+        .replace([' ', '\t', '\n'], "");
+    if is_dirty(path) {
+        format!("{hash}-dirty")
+    } else {
+        hash
+    }
+}
+
+/// Whether the working tree has uncommitted changes.
+pub fn is_dirty(path: &Path) -> bool {
+    let output = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(path)
+        .output()
+        .expect("Failed to execute git command");
+    !output.stdout.is_empty()
+}
+
+/// The project's semantic version, parsed from `git describe --tags`
+/// (the most recent tag, ignoring commits since) and falling back to
+/// `package.version` in `Cargo.toml` when there is no tag.
+pub fn describe_version(path: &Path, cargo_toml: &Table) -> Result<semver::Version, String> {
+    let tagged = std::process::Command::new("git")
+        .args(["describe", "--tags", "--abbrev=0"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let version_str = match tagged {
+        Some(tagged) => tagged,
+        None => cargo_toml
+            .get("package")
+            .and_then(|package| package.get("version"))
+            .and_then(|version| version.as_str())
+            .ok_or_else(|| "Cargo.toml is missing package.version".to_string())?
+            .to_string(),
+    };
+    let version_str = version_str.strip_prefix('v').unwrap_or(&version_str);
+    semver::Version::parse(version_str)
+        .map_err(|e| format!("Failed to parse version `{version_str}`: {e}"))
+}
+
+/// Rewrite `package.version` in `Cargo.toml` in place, preserving the rest
+/// of the file untouched.
+pub fn write_cargo_version(path: &Path, version: &semver::Version) -> Result<(), String> {
+    let cargo_toml_path = path.join("Cargo.toml");
+    let contents = std::fs::read_to_string(&cargo_toml_path)
+        .map_err(|e| format!("Failed to read {}: {e}", cargo_toml_path.display()))?;
+    let mut in_package = false;
+    let mut replaced = false;
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            in_package = trimmed.starts_with("[package]");
+        }
+        if in_package && !replaced && trimmed.starts_with("version") {
+            lines.push(format!("version = \"{version}\""));
+            replaced = true;
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !replaced {
+        return Err("Could not find package.version in Cargo.toml".to_string());
+    }
+    std::fs::write(&cargo_toml_path, lines.join("\n") + "\n")
+        .map_err(|e| format!("Failed to write {}: {e}", cargo_toml_path.display()))
+}
+
+/// Create an annotated git tag `v<version>` pointing at `HEAD`.
+pub fn create_tag(path: &Path, version: &semver::Version) -> Result<(), String> {
+    let tag = format!("v{version}");
+    let status = std::process::Command::new("git")
+        .args(["tag", "-a", &tag, "-m", &tag])
+        .current_dir(path)
+        .status()
+        .map_err(|e| format!("Failed to run git tag: {e}"))?;
+    if !status.success() {
+        return Err(format!(
+            "Failed to create git tag {tag} (it may already exist)"
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use zerocopy::{Immutable, IntoBytes, KnownLayout, TryFromBytes};
+
+pub const MAGIC_WORD: u32 = 0xABCD5432;
+
+/// `reserved` byte layout, beyond the core fields every image carries:
+///   [0]      compression scheme (see `Compression`)
+///   [1..5]   uncompressed payload size, little-endian u32
+///   [5..7]   semver major, little-endian u16
+///   [7..9]   semver minor, little-endian u16
+///   [9..11]  semver patch, little-endian u16
+///   [11..27] semver prerelease identifier, NUL-padded, truncated to 15 bytes
+#[derive(TryFromBytes, IntoBytes, Immutable, PartialEq, KnownLayout, Copy, Clone, Debug)]
+#[repr(packed)]
+pub struct OtaHead {
+    // Always 0xABCD5432, 0b10101011110011010101010000110010
+    pub magic_word: u32,
+    // CRC 16 (IBM SDLC) checksum of Everything that is valid after magic_word
+    pub crc: u16,
+    pub version: [u8; 32],
+    pub project_name: [u8; 16],
+    pub timestamp: u64,
+    // The size of the firmware on flash in bytes (possibly compressed), HEAD size not included
+    pub size: u32,
+    pub reserved: [u8; 446],
+}
+static_assertions::const_assert!(core::mem::size_of::<OtaHead>() == 512);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    Gzip,
+}
+
+impl OtaHead {
+    pub fn compression(&self) -> Option<Compression> {
+        match self.reserved[0] {
+            1 => Some(Compression::Gzip),
+            _ => None,
+        }
+    }
+
+    pub fn set_compression(&mut self, compression: Option<Compression>, uncompressed_size: u32) {
+        self.reserved[0] = match compression {
+            None => 0,
+            Some(Compression::Gzip) => 1,
+        };
+        self.reserved[1..5].copy_from_slice(&uncompressed_size.to_le_bytes());
+    }
+
+    pub fn uncompressed_size(&self) -> u32 {
+        u32::from_le_bytes(self.reserved[1..5].try_into().unwrap())
+    }
+
+    pub fn set_semver(&mut self, version: &semver::Version) {
+        self.reserved[5..7].copy_from_slice(&(version.major as u16).to_le_bytes());
+        self.reserved[7..9].copy_from_slice(&(version.minor as u16).to_le_bytes());
+        self.reserved[9..11].copy_from_slice(&(version.patch as u16).to_le_bytes());
+        let pre = version.pre.as_str().as_bytes();
+        let len = pre.len().min(15);
+        self.reserved[11..27].fill(0);
+        self.reserved[11..11 + len].copy_from_slice(&pre[..len]);
+    }
+
+    pub fn semver(&self) -> semver::Version {
+        let major = u16::from_le_bytes(self.reserved[5..7].try_into().unwrap()) as u64;
+        let minor = u16::from_le_bytes(self.reserved[7..9].try_into().unwrap()) as u64;
+        let patch = u16::from_le_bytes(self.reserved[9..11].try_into().unwrap()) as u64;
+        let pre_bytes = &self.reserved[11..27];
+        let pre_end = pre_bytes.iter().position(|&b| b == 0).unwrap_or(pre_bytes.len());
+        let mut version = semver::Version::new(major, minor, patch);
+        if pre_end > 0 {
+            version.pre = semver::Prerelease::new(&String::from_utf8_lossy(&pre_bytes[..pre_end]))
+                .unwrap_or(semver::Prerelease::EMPTY);
+        }
+        version
+    }
+}
+
+pub fn compression_name(compression: Option<Compression>) -> &'static str {
+    match compression {
+        None => "none",
+        Some(Compression::Gzip) => "gzip",
+    }
+}
+
+pub fn crc16(bytes: &[u8]) -> u16 {
+    const X25: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_IBM_SDLC);
+    X25.checksum(bytes)
+}
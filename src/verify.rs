@@ -0,0 +1,29 @@
+use crate::ota::{self, OtaHead};
+use zerocopy::TryFromBytes;
+
+pub const EXIT_FILE_TOO_SHORT: i32 = 2;
+pub const EXIT_BAD_MAGIC: i32 = 3;
+pub const EXIT_CRC_MISMATCH: i32 = 4;
+
+/// Check only the magic word and CRC-16/IBM-SDLC of an ota bin file,
+/// without printing the descriptive block `decode` shows. Exits 0 if both
+/// checks pass, otherwise with a code specific to the failure reason so
+/// scripts can branch on it.
+pub async fn verify(path: String) {
+    let file_bytes = tokio::fs::read(&path).await.expect("Failed to read file");
+    if file_bytes.len() < 512 {
+        std::process::exit(EXIT_FILE_TOO_SHORT);
+    }
+    let ota_head = match OtaHead::try_read_from_bytes(&file_bytes[0..512]) {
+        Ok(ota_head) => ota_head,
+        Err(_) => std::process::exit(EXIT_BAD_MAGIC),
+    };
+    if ota_head.magic_word != ota::MAGIC_WORD {
+        std::process::exit(EXIT_BAD_MAGIC);
+    }
+    let crc = ota::crc16(&file_bytes[6..]);
+    if crc != ota_head.crc {
+        std::process::exit(EXIT_CRC_MISMATCH);
+    }
+    std::process::exit(0);
+}